@@ -1,21 +1,35 @@
 ﻿using SpotlightDimmer.Models;
+using Microsoft.Win32;
 using System;
 using System.Linq;
 using System.Windows.Forms;
 using System.Windows.Interop;
+using System.Windows.Media.Animation;
+using System.Windows.Threading;
 
 namespace SpotlightDimmer
 {
     public partial class DimmerWindow : Window
     {
-        private readonly string _screenDeviceName;
+        private readonly Screen _screen;
+        private readonly string _screenId;
         private readonly MainWindow _mainWindow;
         private readonly DimmerState _state;
+        private Visibility _targetVisibility = Visibility.Visible;
+        private bool _isClosingFromMainWindow;
         // Makes the window transparent and unclickable
         private const int WS_EX_TRANSPARENT = 0x00000020;
         // Makes the window not appear on alt+tab
         private const int WS_EX_TOOLWINDOW = 0x00000080;
         private const int GWL_EXSTYLE = (-20);
+        // Used to put the window back on top of other topmost windows without moving, resizing or activating it
+        private static readonly IntPtr HWND_TOPMOST = new IntPtr(-1);
+        private const uint SWP_NOSIZE = 0x0001;
+        private const uint SWP_NOMOVE = 0x0002;
+        private const uint SWP_NOACTIVATE = 0x0010;
+        // Hides the window from screenshots and recordings (only available from Windows 10 version 2004 onwards)
+        private const uint WDA_NONE = 0x00000000;
+        private const uint WDA_EXCLUDEFROMCAPTURE = 0x00000011;
 
 
         // Methods to make the window transparent to clicks and not appear on alt+tab menus
@@ -23,45 +37,218 @@ namespace SpotlightDimmer
         static extern int GetWindowLong(IntPtr hwnd, int index);
         [DllImport("user32.dll")]
         static extern int SetWindowLong(IntPtr hwnd, int index, int newStyle);
+        [DllImport("user32.dll")]
+        static extern bool SetWindowPos(IntPtr hwnd, IntPtr hwndInsertAfter, int x, int y, int cx, int cy, uint flags);
+        [DllImport("user32.dll", SetLastError = true)]
+        static extern bool SetWindowDisplayAffinity(IntPtr hwnd, uint affinity);
+
+        // Method to check where the window actually is, regardless of the position WPF was asked for
+        [DllImport("user32.dll")]
+        static extern bool GetWindowRect(IntPtr hwnd, ref RECT rect);
+
+        // Method to check which window receives the clicks on a given point
+        private struct POINT
+        {
+            public int x;
+            public int y;
+        }
+        [DllImport("user32.dll")]
+        static extern IntPtr WindowFromPoint(POINT point);
 
         public DimmerWindow(Screen screen, DimmerState state, MainWindow mainWindow)
         {
             InitializeComponent();
 
             _mainWindow = mainWindow;
-            _screenDeviceName = screen.DeviceName;
+            _screen = screen;
+            _screenId = WindowsEventsManager.GetScreenId(screen);
             _state = state;
             DataContext = _state;
 
-            Left = screen.Bounds.Left;
-            Top = screen.Bounds.Top;
-            Width = screen.Bounds.Width;
-            Height = screen.Bounds.Height;
+            ApplyBounds();
+            ApplyBackground();
 
             UpdateVisibilityOnFocusedScreenChange();
+            SystemEvents.UserPreferenceChanged += SystemEvents_UserPreferenceChanged;
 
-            Show();
+            // The window is set up without being shown, so a dimmer for a screen that shouldn't be dimmed never flashes on it
+            new WindowInteropHelper(this).EnsureHandle();
+            ShowRelatedToFocus();
         }
 
         private void UpdateVisibilityOnFocusedScreenChange()
         {
-            _state.PropertyChanged += (object? sender, PropertyChangedEventArgs e) =>
+            _state.PropertyChanged += State_PropertyChanged;
+        }
+
+        private void State_PropertyChanged(object? sender, PropertyChangedEventArgs e)
+        {
+            if (e.PropertyName == nameof(_state.FocusedScreen) ||
+                e.PropertyName == nameof(_state.IsDimmingEnabled) ||
+                e.PropertyName == nameof(_state.ExcludedDisplays) ||
+                e.PropertyName == nameof(_state.IsFullscreenAppFocused) ||
+                e.PropertyName == nameof(_state.AutoSuspendFullscreen))
             {
-                if (e.PropertyName == nameof(_state.FocusedScreen))
-                {
-                    SetVisibilityRelatedToFocus();
-                }
-            };
+                SetVisibilityRelatedToFocus();
+            }
+            else if (e.PropertyName == nameof(_state.ExcludeFromCapture))
+            {
+                ApplyCaptureExclusion();
+            }
+            else if (e.PropertyName == nameof(_state.RespectWorkArea))
+            {
+                ApplyBounds();
+            }
+            else if (e.PropertyName == nameof(_state.SelectedBrush) ||
+                e.PropertyName == nameof(_state.DisplayColors))
+            {
+                ApplyBackground();
+            }
+        }
+
+        /// <summary>
+        /// Paints the window with the color given to its screen, or with the selected color when it has none.
+        /// </summary>
+        private void ApplyBackground()
+        {
+            Background = _state.GetBrushForScreen(_screenId);
+        }
+
+        /// <summary>
+        /// Covers either the whole screen or, when the taskbar should be left undimmed, only its working area.
+        /// </summary>
+        private void ApplyBounds()
+        {
+            var bounds = _state.RespectWorkArea ? _screen.WorkingArea : _screen.Bounds;
+
+            // The screen bounds are in pixels while WPF positions windows in scaled units, so once there is a window handle it's placed in pixels directly
+            var hwnd = new WindowInteropHelper(this).Handle;
+            if (hwnd != IntPtr.Zero)
+            {
+                SetWindowPos(hwnd, HWND_TOPMOST, bounds.Left, bounds.Top, bounds.Width, bounds.Height, SWP_NOACTIVATE);
+                return;
+            }
+
+            Left = bounds.Left;
+            Top = bounds.Top;
+            Width = bounds.Width;
+            Height = bounds.Height;
+        }
+
+        private void SystemEvents_UserPreferenceChanged(object sender, UserPreferenceChangedEventArgs e)
+        {
+            // Moving, resizing or auto hiding the taskbar changes the working area and is notified as a desktop preference change.
+            // The screens only refresh their working area in their own handler of this event, which may run after this one, so the bounds are applied afterwards.
+            if (e.Category == UserPreferenceCategory.Desktop && _state.RespectWorkArea)
+                Dispatcher.BeginInvoke(new Action(ApplyBounds));
         }
 
         public void SetVisibilityRelatedToFocus()
         {
-            if (_state.FocusedScreen.DeviceName == _screenDeviceName)
-                Visibility = Visibility.Hidden;
-            else if (!Screen.AllScreens.Any(screen => screen.DeviceName == _screenDeviceName))
-                Visibility = Visibility.Hidden;
+            FadeTo(ShouldBeVisible() ? Visibility.Visible : Visibility.Hidden);
+        }
+
+        /// <summary>
+        /// Shows or hides the window right away, without fading, depending on whether its screen should be dimmed.
+        /// </summary>
+        private void ShowRelatedToFocus()
+        {
+            BeginAnimation(OpacityProperty, null);
+            Opacity = 1;
+            if (ShouldBeVisible())
+            {
+                _targetVisibility = Visibility.Visible;
+                Show();
+                ReassertTopmost();
+            }
+            else
+            {
+                _targetVisibility = Visibility.Hidden;
+                Hide();
+            }
+        }
+
+        private bool ShouldBeVisible()
+        {
+            if (!_state.IsDimmingEnabled)
+                return false;
+            else if (_state.IsDisplayExcluded(_screenId))
+                return false;
+            else if (_state.AutoSuspendFullscreen && _state.IsFullscreenAppFocused)
+                return false;
+            else if (WindowsEventsManager.IsSameLogicalScreen(_state.FocusedScreen, _screen))
+                return false;
+            else if (!WindowsEventsManager.GetLogicalScreens().Any(screen => screen.DeviceName == _screen.DeviceName))
+                return false;
             else
+                return true;
+        }
+
+        /// <summary>
+        /// Shows or hides the window, fading its opacity over the configured animation duration.
+        /// A new fade replaces the one in progress starting from the current opacity, so quickly switching focus never leaves the window stuck half faded.
+        /// </summary>
+        private void FadeTo(Visibility visibility)
+        {
+            _targetVisibility = visibility;
+            var isVisible = visibility == Visibility.Visible;
+            var isReshown = isVisible && Visibility != Visibility.Visible;
+
+            if (_state.AnimationMilliseconds <= 0)
+            {
+                BeginAnimation(OpacityProperty, null);
+                Opacity = 1;
+                Visibility = visibility;
+                if (isReshown)
+                {
+                    ReassertTopmost();
+                    Dispatcher.BeginInvoke(new Action(EnsureClickThrough), DispatcherPriority.ContextIdle);
+                }
+                return;
+            }
+
+            if (isReshown)
+            {
+                BeginAnimation(OpacityProperty, null);
+                Opacity = 0;
                 Visibility = Visibility.Visible;
+                ReassertTopmost();
+            }
+
+            var animation = new DoubleAnimation(isVisible ? 1 : 0, TimeSpan.FromMilliseconds(_state.AnimationMilliseconds));
+            animation.Completed += (object? sender, EventArgs e) =>
+            {
+                // A newer fade may have started since this one, in which case it decides the visibility
+                if (_targetVisibility != Visibility.Visible)
+                    Visibility = Visibility.Hidden;
+                // Checked once fully shown, since the fully transparent parts of a window never catch clicks
+                else if (isReshown)
+                    EnsureClickThrough();
+            };
+            BeginAnimation(OpacityProperty, animation);
+        }
+
+        /// <summary>
+        /// Brings the window back over other topmost windows, such as screen recorders or sticky notes.
+        /// An app that keeps re-asserting its own topmost position can still end up over the dimmer between calls.
+        /// </summary>
+        public void ReassertTopmost()
+        {
+            if (Visibility != Visibility.Visible)
+                return;
+
+            var hwnd = new WindowInteropHelper(this).Handle;
+            SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE);
+        }
+
+        /// <summary>
+        /// Describes the screen the window dims and where the window currently is, to tell the dimmers apart while debugging.
+        /// </summary>
+        public string Describe()
+        {
+            var rect = new RECT();
+            GetWindowRect(new WindowInteropHelper(this).Handle, ref rect);
+            return $"{_screen.DeviceName} ({_targetVisibility}) covering {rect.left},{rect.top} to {rect.right},{rect.bottom}\r\nId: {_screenId}";
         }
 
         public static void SetWindowExTransparent(IntPtr hwnd)
@@ -70,11 +257,119 @@ namespace SpotlightDimmer
             _ = SetWindowLong(hwnd, GWL_EXSTYLE, extendedStyle | WS_EX_TRANSPARENT | WS_EX_TOOLWINDOW);
         }
 
+        /// <summary>
+        /// Sets whether the window shows up on screenshots and screen recordings.
+        /// </summary>
+        /// <returns>False if Windows refused the change or doesn't support excluding windows from capture.</returns>
+        public static bool SetExcludedFromCapture(IntPtr hwnd, bool excluded)
+        {
+            if (excluded && !WindowsEventsManager.IsCaptureExclusionSupported)
+                return false;
+
+            return SetWindowDisplayAffinity(hwnd, excluded ? WDA_EXCLUDEFROMCAPTURE : WDA_NONE);
+        }
+
+        private void ApplyCaptureExclusion()
+        {
+            var hwnd = new WindowInteropHelper(this).Handle;
+            if (hwnd == IntPtr.Zero)
+                return;
+
+            if (_state.ExcludeFromCapture && !WindowsEventsManager.IsCaptureExclusionSupported)
+            {
+                _state.DebugInfo = $"Hiding from screen capture needs Windows 10 version 2004 or later, so the dimmer on {_screen.DeviceName} will keep showing up on screen captures.";
+                return;
+            }
+
+            if (!SetExcludedFromCapture(hwnd, _state.ExcludeFromCapture))
+                _state.DebugInfo = $"Could not change the screen capture visibility of the dimmer on {_screen.DeviceName} (error {Marshal.GetLastWin32Error()}). It will keep showing up on screen captures.";
+        }
+
         protected override void OnSourceInitialized(EventArgs e)
         {
             base.OnSourceInitialized(e);
             var hwnd = new WindowInteropHelper(this).Handle;
             SetWindowExTransparent(hwnd);
+            ApplyCaptureExclusion();
+            ApplyBounds();
+        }
+
+        /// <summary>
+        /// Checks that the window lets clicks through to the windows below it.
+        /// It checks the extended styles and, when the window is visible, that the window under its center isn't itself.
+        /// </summary>
+        public bool VerifyClickThrough()
+        {
+            var hwnd = new WindowInteropHelper(this).Handle;
+            var extendedStyle = GetWindowLong(hwnd, GWL_EXSTYLE);
+            if ((extendedStyle & WS_EX_TRANSPARENT) == 0)
+                return false;
+
+            if (Visibility != Visibility.Visible)
+                return true;
+
+            var center = new POINT
+            {
+                x = _screen.Bounds.Left + _screen.Bounds.Width / 2,
+                y = _screen.Bounds.Top + _screen.Bounds.Height / 2
+            };
+            return WindowFromPoint(center) != hwnd;
+        }
+
+        protected override void OnContentRendered(EventArgs e)
+        {
+            base.OnContentRendered(e);
+            EnsureClickThrough();
+        }
+
+        /// <summary>
+        /// Applies the click-through styles again when the window isn't letting clicks through, and warns when that didn't fix it.
+        /// </summary>
+        private void EnsureClickThrough()
+        {
+            if (VerifyClickThrough())
+                return;
+
+            _state.DebugInfo = $"Warning: the dimmer on {_screen.DeviceName} is not letting clicks through, applying its click-through styles again";
+            SetWindowExTransparent(new WindowInteropHelper(this).Handle);
+            if (!VerifyClickThrough())
+                _state.DebugInfo = $"Warning: the dimmer on {_screen.DeviceName} is still not letting clicks through. Disable the dimming or recreate the dimmers to use that screen.";
+        }
+
+        /// <summary>
+        /// Closes the window as part of the main window's lifecycle, such as when the dimmers are recreated or the program exits.
+        /// </summary>
+        public void CloseFromMainWindow()
+        {
+            _isClosingFromMainWindow = true;
+            Close();
+        }
+
+        protected override void OnClosing(CancelEventArgs e)
+        {
+            base.OnClosing(e);
+            if (_isClosingFromMainWindow)
+                return;
+
+            // Something else, such as taskkill without /F, asked the dimmer to close, so the whole program exits through its normal cleanup.
+            // The main window closes this dimmer while cleaning up, which WPF refuses while the dimmer is still closing, so it waits until this close is cancelled
+            e.Cancel = true;
+            Dispatcher.BeginInvoke(new Action(_mainWindow.Close));
+        }
+
+        protected override void OnDpiChanged(DpiScale oldDpi, DpiScale newDpi)
+        {
+            base.OnDpiChanged(oldDpi, newDpi);
+            // WPF resizes the window to keep its size in scaled units, but the dimmer must keep covering the same pixels, which don't change with the scaling
+            Dispatcher.BeginInvoke(new Action(ApplyBounds));
+        }
+
+        protected override void OnClosed(EventArgs e)
+        {
+            // The state outlives the window when the dimmers are recreated, so it must stop notifying it
+            _state.PropertyChanged -= State_PropertyChanged;
+            SystemEvents.UserPreferenceChanged -= SystemEvents_UserPreferenceChanged;
+            base.OnClosed(e);
         }
 
         private void ScreenDimmerWindow_Activated(object sender, EventArgs e)
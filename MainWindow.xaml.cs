@@ -4,6 +4,7 @@ using SpotlightDimmer.Models;
 using System;
 using System.Windows.Interop;
 using System.Windows.Media.Imaging;
+using System.Windows.Threading;
 
 namespace SpotlightDimmer
 {
@@ -12,18 +13,47 @@ namespace SpotlightDimmer
         public DimmerSettings _dimmerSettings;
         public WindowsEventsManager _dimmerStateManager;
         public DimmerState _state;
-        private Dictionary<string, Window> _dimmerWindowsByScreen;
+        private DimmerEventLog _eventLog;
+        private Dictionary<string, DimmerWindow> _dimmerWindowsByScreen;
+        private DispatcherTimer _topmostRefreshTimer;
+        private DispatcherTimer _topmostReassertionTimer;
+        private DispatcherTimer? _screensRetryTimer;
+        private int _screensRetryCount;
         private NotifyIcon _notifyIcon;
+        private bool _isCleanedUp;
+        private bool _isRecreatingTheDimmersPending;
+        private IntPtr _hwnd;
+
+        // Foreground changes can come in bursts, so the dimmers are brought back on top once they have stopped for this long
+        private static readonly TimeSpan TopmostReassertionDelay = TimeSpan.FromMilliseconds(250);
+
+        // How long the startup waits for a focus change when the dimmers are deferred before creating them anyway
+        private static readonly TimeSpan DeferredDimmersTimeout = TimeSpan.FromSeconds(30);
+
+        // How many times the dimmers are created again when no usable screen is found, waiting twice as long each time
+        private const int MaxScreensRetries = 5;
+
+        // Methods to register the global hotkeys
+        private const int WM_HOTKEY = 0x0312;
+        private const int PauseHotkeyId = 1;
+        // Sent when the screens are added, removed, rearranged or change resolution. The dimmers handle scaling changes of their own screens
+        private const int WM_DISPLAYCHANGE = 0x007E;
+        [DllImport("user32.dll", SetLastError = true)]
+        static extern bool RegisterHotKey(IntPtr hwnd, int id, uint modifiers, uint virtualKey);
+        [DllImport("user32.dll")]
+        static extern bool UnregisterHotKey(IntPtr hwnd, int id);
 
         public MainWindow()
         {
             InitializeComponent();
 
-            SetApplicationIcon();
             BuildTheViewModel();
+            SetApplicationIcon();
             SetMinimizeToTrayOptions();
-            CreateTheDimmerWindows();
+            CreateTheDimmerWindowsOnStartup();
+            SetTopmostRefreshTimer();
             Closing += OnClosing;
+            System.Windows.Application.Current.SessionEnding += OnSessionEnding;
         }
 
         private void BuildTheViewModel()
@@ -31,29 +61,223 @@ namespace SpotlightDimmer
             _state = new DimmerState();
             _dimmerStateManager = new WindowsEventsManager(_state);
             _dimmerSettings = new DimmerSettings(_state);
+            _eventLog = new DimmerEventLog(_state);
             DataContext = _state;
         }
 
+        /// <summary>
+        /// Creates the dimmers right away or, when they are deferred, on the first focus change or once the wait times out.
+        /// </summary>
+        private void CreateTheDimmerWindowsOnStartup()
+        {
+            _dimmerWindowsByScreen = new Dictionary<string, DimmerWindow>();
+            if (!_state.DeferInitialDimmers)
+            {
+                CreateTheDimmerWindows();
+                return;
+            }
+
+            _state.DebugInfo = $"Waiting for a focus change, or {DeferredDimmersTimeout.TotalSeconds} seconds, before creating the dimmers";
+            var timeoutTimer = new DispatcherTimer { Interval = DeferredDimmersTimeout };
+            EventHandler? createTheDimmers = null;
+            createTheDimmers = (object? sender, EventArgs e) =>
+            {
+                timeoutTimer.Stop();
+                _dimmerStateManager.ForegroundChanged -= createTheDimmers;
+                // Created once the focus change is handled, so the dimmers start out knowing the focused screen
+                Dispatcher.InvokeAsync(() =>
+                {
+                    if (!_isCleanedUp)
+                        CreateTheDimmerWindows();
+                });
+            };
+            timeoutTimer.Tick += createTheDimmers;
+            _dimmerStateManager.ForegroundChanged += createTheDimmers;
+            timeoutTimer.Start();
+        }
+
         protected void CreateTheDimmerWindows()
         {
-            _dimmerWindowsByScreen ??= new Dictionary<string, Window>();
+            _dimmerWindowsByScreen ??= new Dictionary<string, DimmerWindow>();
 
-            foreach (var dimmerWindow in _dimmerWindowsByScreen.Values)
-                dimmerWindow.Hide();
+            _state.DebugInfo = $"Screens found:\r\n{String.Join("\r\n", Screen.AllScreens.Select(WindowsEventsManager.DescribeScreen))}";
 
-            foreach (var screen in Screen.AllScreens)
+            var usableScreensCount = 0;
+            foreach (var screen in WindowsEventsManager.GetLogicalScreens())
             {
-                if (!_dimmerWindowsByScreen.ContainsKey(screen.DeviceName))
+                if (!WindowsEventsManager.HasSaneBounds(screen))
                 {
-                    var dimmerWindow = new DimmerWindow(screen, _state, this);
-                    _dimmerWindowsByScreen.Add(screen.DeviceName, dimmerWindow);
-                    dimmerWindow.Show();
-                }
-                else
-                {
-                    _dimmerWindowsByScreen[screen.DeviceName].Show();
+                    _state.DebugInfo = $"Skipping screen {screen.DeviceName} with invalid size {screen.Bounds.Width}x{screen.Bounds.Height}. Recreate the dimmers once the display settles.";
+                    continue;
                 }
+                usableScreensCount++;
+
+                // New dimmers only show up if their screen should be dimmed
+                if (!_dimmerWindowsByScreen.ContainsKey(screen.DeviceName))
+                    _dimmerWindowsByScreen.Add(screen.DeviceName, new DimmerWindow(screen, _state, this));
+            }
+
+            // The existing dimmers fade in or out to follow the screens that were added, removed or mirrored since they were created
+            foreach (var dimmerWindow in _dimmerWindowsByScreen.Values)
+                dimmerWindow.SetVisibilityRelatedToFocus();
+
+            LogTheDimmerWindows();
+
+            if (usableScreensCount == 0)
+                RetryCreatingTheDimmerWindows();
+            else
+                _screensRetryCount = 0;
+        }
+
+        private void LogTheDimmerWindows()
+        {
+            _state.DebugInfo = $"Dimmers:\r\n{String.Join("\r\n", _dimmerWindowsByScreen.Values.Select(dimmerWindow => dimmerWindow.Describe()))}";
+        }
+
+        /// <summary>
+        /// Schedules the dimmers to be created again when no usable screen was found, which happens for a moment while a remote desktop session is handed over.
+        /// </summary>
+        private void RetryCreatingTheDimmerWindows()
+        {
+            if (_screensRetryCount >= MaxScreensRetries)
+            {
+                _state.DebugInfo = $"No usable screen found after {MaxScreensRetries} retries. Recreate the dimmers once the display settles.";
+                _screensRetryCount = 0;
+                return;
             }
+
+            var delay = TimeSpan.FromSeconds(Math.Pow(2, _screensRetryCount));
+            _screensRetryCount++;
+            _state.DebugInfo = $"No usable screen found, trying again in {delay.TotalSeconds} seconds";
+
+            _screensRetryTimer?.Stop();
+            var retryTimer = new DispatcherTimer { Interval = delay };
+            retryTimer.Tick += (object? sender, EventArgs e) =>
+            {
+                retryTimer.Stop();
+                CreateTheDimmerWindows();
+            };
+            _screensRetryTimer = retryTimer;
+            _screensRetryTimer.Start();
+        }
+
+        private void RecreateTheDimmerWindows()
+        {
+            foreach (var dimmerWindow in _dimmerWindowsByScreen.Values)
+                dimmerWindow.CloseFromMainWindow();
+            _dimmerWindowsByScreen.Clear();
+
+            CreateTheDimmerWindows();
+            _state.DebugInfo = $"Recreated {_dimmerWindowsByScreen.Count} dimmer windows";
+            _eventLog.Write("dimmers-recreated");
+        }
+
+        protected override void OnSourceInitialized(EventArgs e)
+        {
+            base.OnSourceInitialized(e);
+            _hwnd = new WindowInteropHelper(this).Handle;
+            HwndSource.FromHwnd(_hwnd).AddHook(WndProc);
+            SetPauseHotkey();
+        }
+
+        private void SetPauseHotkey()
+        {
+            RegisterPauseHotkey();
+            _state.PropertyChanged += (object? sender, PropertyChangedEventArgs e) =>
+            {
+                if (e.PropertyName == nameof(_state.PauseHotkey))
+                    RegisterPauseHotkey();
+            };
+        }
+
+        private void RegisterPauseHotkey()
+        {
+            UnregisterHotKey(_hwnd, PauseHotkeyId);
+            if (String.IsNullOrWhiteSpace(_state.PauseHotkey))
+                return;
+
+            if (!Hotkey.TryParse(_state.PauseHotkey, out var hotkey) || hotkey == null)
+            {
+                _state.DebugInfo = $"Invalid pause hotkey \"{_state.PauseHotkey}\". Use modifiers followed by a key, such as Ctrl+Alt+D, or a function key on its own";
+                return;
+            }
+
+            if (!RegisterHotKey(_hwnd, PauseHotkeyId, hotkey.Modifiers | Hotkey.MOD_NOREPEAT, hotkey.VirtualKey))
+                _state.DebugInfo = $"Could not register the pause hotkey {_state.PauseHotkey} (error {Marshal.GetLastWin32Error()}). It may already be in use by another program.";
+        }
+
+        private IntPtr WndProc(IntPtr hwnd, int msg, IntPtr wParam, IntPtr lParam, ref bool handled)
+        {
+            if (msg == WM_HOTKEY && wParam.ToInt32() == PauseHotkeyId)
+            {
+                _state.IsDimmingEnabled = !_state.IsDimmingEnabled;
+                handled = true;
+            }
+            else if (msg == WM_DISPLAYCHANGE)
+            {
+                RecreateTheDimmerWindowsLater();
+            }
+
+            return IntPtr.Zero;
+        }
+
+        /// <summary>
+        /// Recreates the dimmers once the pending messages are handled, so the screens are read after they settle and a burst of display changes recreates them only once.
+        /// </summary>
+        private void RecreateTheDimmerWindowsLater()
+        {
+            if (_isRecreatingTheDimmersPending)
+                return;
+            _isRecreatingTheDimmersPending = true;
+
+            Dispatcher.InvokeAsync(() =>
+            {
+                _isRecreatingTheDimmersPending = false;
+                if (!_isCleanedUp)
+                    RecreateTheDimmerWindows();
+            }, DispatcherPriority.Background);
+        }
+
+        private void SetTopmostRefreshTimer()
+        {
+            _topmostRefreshTimer = new DispatcherTimer();
+            _topmostRefreshTimer.Tick += (object? sender, EventArgs e) => ReassertTopmost();
+
+            UpdateTopmostRefreshTimer();
+            _state.PropertyChanged += (object? sender, PropertyChangedEventArgs e) =>
+            {
+                if (e.PropertyName == nameof(_state.TopmostRefreshSeconds))
+                    UpdateTopmostRefreshTimer();
+            };
+
+            // Another topmost window may have been brought over the dimmers along with the new foreground window
+            _topmostReassertionTimer = new DispatcherTimer { Interval = TopmostReassertionDelay };
+            _topmostReassertionTimer.Tick += (object? sender, EventArgs e) =>
+            {
+                _topmostReassertionTimer.Stop();
+                ReassertTopmost();
+            };
+            _dimmerStateManager.ForegroundChanged += (object? sender, EventArgs e) =>
+            {
+                _topmostReassertionTimer.Stop();
+                _topmostReassertionTimer.Start();
+            };
+        }
+
+        private void ReassertTopmost()
+        {
+            foreach (var dimmerWindow in _dimmerWindowsByScreen.Values)
+                dimmerWindow.ReassertTopmost();
+        }
+
+        private void UpdateTopmostRefreshTimer()
+        {
+            _topmostRefreshTimer.Stop();
+            if (_state.TopmostRefreshSeconds <= 0)
+                return;
+
+            _topmostRefreshTimer.Interval = TimeSpan.FromSeconds(_state.TopmostRefreshSeconds);
+            _topmostRefreshTimer.Start();
         }
 
         private void SetApplicationIcon()
@@ -81,6 +305,13 @@ namespace SpotlightDimmer
 
             // Handle the DoubleClick event of the NotifyIcon
             _notifyIcon.Click += NotifyIcon_Click;
+
+            // Show on the tray when the dimming is paused
+            _state.PropertyChanged += (object? sender, PropertyChangedEventArgs e) =>
+            {
+                if (e.PropertyName == nameof(_state.IsDimmingEnabled))
+                    _notifyIcon.Text = _state.IsDimmingEnabled ? "Spotlight Dimmer" : "Spotlight Dimmer (paused)";
+            };
         }
 
         private void SaveSettingsButton_Click(object? sender, RoutedEventArgs e)
@@ -88,6 +319,21 @@ namespace SpotlightDimmer
             _dimmerSettings.SaveSettings();
         }
 
+        private void ReloadSettingsButton_Click(object? sender, RoutedEventArgs e)
+        {
+            _dimmerSettings.ReloadSettings();
+        }
+
+        private void RecreateDimmersButton_Click(object? sender, RoutedEventArgs e)
+        {
+            RecreateTheDimmerWindows();
+        }
+
+        private void ShowDimmersButton_Click(object? sender, RoutedEventArgs e)
+        {
+            LogTheDimmerWindows();
+        }
+
         private void Window_Activated(object? sender, EventArgs e)
         {
             this.Activate();
@@ -119,16 +365,44 @@ namespace SpotlightDimmer
 
         private void OnClosing(object? sender, CancelEventArgs e)
         {
+            CleanUp();
+        }
+
+        private void OnSessionEnding(object? sender, SessionEndingCancelEventArgs e)
+        {
+            // The windows aren't guaranteed to go through Closing when Windows logs off or shuts down
+            CleanUp();
+        }
+
+        private void CleanUp()
+        {
+            if (_isCleanedUp)
+                return;
+            _isCleanedUp = true;
+
+            _topmostRefreshTimer.Stop();
+            _topmostReassertionTimer.Stop();
+            _screensRetryTimer?.Stop();
+            UnregisterHotKey(_hwnd, PauseHotkeyId);
+
             foreach (var childWindow in _dimmerWindowsByScreen.Values)
-                childWindow.Close();
+                childWindow.CloseFromMainWindow();
 
             _dimmerStateManager.Dispose();
+            _eventLog.Dispose();
             _notifyIcon.Dispose();
         }
 
         private System.Drawing.Icon GetSpotlightDimmerIcon()
         {
             using var stream = typeof(MainWindow).Assembly.GetManifestResourceStream("SpotlightDimmer.ico");
+            if (stream == null)
+            {
+                // Builds that don't embed the icon still have to run, so they use the default application icon
+                _state.DebugInfo = "Warning: the SpotlightDimmer.ico resource was not found, using the default application icon";
+                return System.Drawing.SystemIcons.Application;
+            }
+
             return new System.Drawing.Icon(stream);
         }
     }
@@ -0,0 +1,20 @@
+﻿namespace SpotlightDimmer.Models;
+
+/// <summary>
+/// What decides which screen is kept undimmed.
+/// </summary>
+public enum ActiveScreenSource
+{
+    /// <summary>
+    /// The screen of the window that has the keyboard focus.
+    /// </summary>
+    KeyboardFocus,
+    /// <summary>
+    /// The screen the mouse cursor is on.
+    /// </summary>
+    MousePosition,
+    /// <summary>
+    /// Whichever of the focused window or the mouse cursor moved to another screen most recently.
+    /// </summary>
+    Either
+}
@@ -0,0 +1,88 @@
+﻿using System.IO;
+using System.Text.Json;
+
+namespace SpotlightDimmer.Models;
+
+public class DimmerEventLog : IDisposable
+{
+    private readonly DimmerState _state;
+    private StreamWriter? _writer;
+    private string _openedPath = "";
+    private string? _lastFocusedScreenId;
+
+    /// <summary>
+    /// This class is responsible for appending the dimmer state changes as newline-delimited JSON to the configured event log file, so other tools can follow them.
+    /// </summary>
+    /// <param name="state">The state of the program so it can log its changes.</param>
+    public DimmerEventLog(DimmerState state)
+    {
+        _state = state;
+        _state.PropertyChanged += State_PropertyChanged;
+    }
+
+    private void State_PropertyChanged(object? sender, PropertyChangedEventArgs e)
+    {
+        if (e.PropertyName == nameof(_state.FocusedScreen))
+        {
+            // The focused screen is set again on every move or resize of the focused window, which isn't a change worth logging
+            if (_state.FocusedScreenId == _lastFocusedScreenId)
+                return;
+            _lastFocusedScreenId = _state.FocusedScreenId;
+            Write("focus-changed");
+        }
+        else if (e.PropertyName == nameof(_state.IsDimmingEnabled))
+            Write(_state.IsDimmingEnabled ? "dimming-enabled" : "dimming-disabled");
+    }
+
+    public void Write(string eventName)
+    {
+        var writer = GetWriter();
+        if (writer == null)
+            return;
+
+        try
+        {
+            var entry = new
+            {
+                timestamp = DateTimeOffset.Now,
+                @event = eventName,
+                display_id = _state.FocusedScreenId,
+                window_title = _state.ActiveWindowInfo.Title
+            };
+            writer.WriteLine(JsonSerializer.Serialize(entry));
+            writer.Flush();
+        }
+        catch (Exception)
+        {
+            // The event log is optional, so failing to write it must never get in the way of the dimming
+        }
+    }
+
+    private StreamWriter? GetWriter()
+    {
+        if (_state.EventLogPath == _openedPath)
+            return _writer;
+
+        _writer?.Dispose();
+        _writer = null;
+        _openedPath = _state.EventLogPath;
+        if (String.IsNullOrWhiteSpace(_openedPath))
+            return null;
+
+        try
+        {
+            _writer = new StreamWriter(_openedPath, append: true);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = $"Could not open the event log {_openedPath}, events won't be logged: {ex.Message}";
+        }
+        return _writer;
+    }
+
+    public void Dispose()
+    {
+        _state.PropertyChanged -= State_PropertyChanged;
+        _writer?.Dispose();
+    }
+}
@@ -0,0 +1,52 @@
+﻿using Brush = System.Windows.Media.Brush;
+using Color = System.Windows.Media.Color;
+using Pen = System.Windows.Media.Pen;
+using Point = System.Windows.Point;
+
+namespace SpotlightDimmer.Models;
+
+/// <summary>
+/// A pattern drawn on top of the dimmed screens so they can be told apart from the focused one at a glance.
+/// </summary>
+public enum DimmerPattern
+{
+    None,
+    Dots,
+    DiagonalLines
+}
+
+public static class DimmerPatternBrush
+{
+    private const double TileSize = 16;
+
+    /// <summary>
+    /// Creates the brush used to paint the dimmer windows.
+    /// The pattern is drawn with the selected color over a fill of the same color, so it shows as a slightly darker shade of the dim.
+    /// </summary>
+    /// <param name="pattern">The pattern to draw, or <see cref="DimmerPattern.None"/> for a flat fill.</param>
+    /// <param name="color">The selected dimming color, including its transparency.</param>
+    public static Brush Create(DimmerPattern pattern, Color color)
+    {
+        var fill = new SolidColorBrush(color);
+        if (pattern == DimmerPattern.None)
+            return fill;
+
+        var tile = new Rect(0, 0, TileSize, TileSize);
+        var drawing = new DrawingGroup();
+        drawing.Children.Add(new GeometryDrawing(fill, null, new RectangleGeometry(tile)));
+        drawing.Children.Add(pattern switch
+        {
+            DimmerPattern.Dots => new GeometryDrawing(fill, null, new EllipseGeometry(new Point(TileSize / 2, TileSize / 2), 2, 2)),
+            _ => new GeometryDrawing(null, new Pen(fill, 2), new LineGeometry(new Point(0, TileSize), new Point(TileSize, 0))),
+        });
+
+        return new DrawingBrush(drawing)
+        {
+            TileMode = TileMode.Tile,
+            Viewport = tile,
+            ViewportUnits = BrushMappingMode.Absolute,
+            Viewbox = tile,
+            ViewboxUnits = BrushMappingMode.Absolute
+        };
+    }
+}
@@ -5,7 +5,7 @@ namespace SpotlightDimmer.Models;
 
 public class DimmerSettings: INotifyPropertyChanged
 {
-    private readonly Configuration _configuration;
+    private Configuration _configuration;
     private readonly DimmerState _state;
 
     /// <summary>
@@ -16,10 +16,43 @@ public class DimmerSettings: INotifyPropertyChanged
     {
         _state = state;
         _configuration = ConfigurationManager.OpenExeConfiguration(ConfigurationUserLevel.None);
+        LoadSettings();
+        SaveMigratedExcludedDisplays();
+    }
+
+    /// <summary>
+    /// Reads the settings file again and applies it, discarding unsaved changes. Useful after editing the file by hand.
+    /// </summary>
+    public void ReloadSettings()
+    {
+        _configuration = ConfigurationManager.OpenExeConfiguration(ConfigurationUserLevel.None);
+        LoadSettings();
+    }
+
+    private void LoadSettings()
+    {
         _state.SelectedColor = GetColorFromSettings();
+        _state.IsDimmingEnabled = GetDimmingEnabledFromSettings();
+        _state.ExcludedDisplays = GetExcludedDisplaysFromSettings();
+        _state.DisplayColors = GetDisplayColorsFromSettings();
+        _state.IgnoredWindowTitles = GetIgnoredWindowTitlesFromSettings();
+        _state.IgnoredProcesses = GetIgnoredProcessesFromSettings();
+        _state.PauseHotkey = GetPauseHotkeyFromSettings();
+        _state.EventLogPath = GetEventLogPathFromSettings();
         _state.Topmost = GetTopmostFromSettings();
         _state.MinimizeToTray = GetMinimizeToTrayFromSettings();
+        _state.Pattern = GetPatternFromSettings();
+        _state.TopmostRefreshSeconds = GetTopmostRefreshSecondsFromSettings();
+        _state.AnimationMilliseconds = GetAnimationMillisecondsFromSettings();
+        _state.FocusDebounceMilliseconds = GetFocusDebounceMillisecondsFromSettings();
+        _state.ScreenEdgeTolerance = GetScreenEdgeToleranceFromSettings();
+        _state.ActiveBy = GetActiveByFromSettings();
+        _state.ExcludeFromCapture = GetExcludeFromCaptureFromSettings();
+        _state.AutoSuspendFullscreen = GetAutoSuspendFullscreenFromSettings();
+        _state.RespectWorkArea = GetRespectWorkAreaFromSettings();
+        _state.DeferInitialDimmers = GetDeferInitialDimmersFromSettings();
         _state.DebugInfo = $"Saved Settings: \r\n{GetSavedSettings()}";
+        WarnAboutTransparency();
     }
 
     public event PropertyChangedEventHandler? PropertyChanged;
@@ -46,6 +79,9 @@ public class DimmerSettings: INotifyPropertyChanged
                 (byte)(backgroundColorIntValue & 0xff)
             );
 
+            if (backgroundColor.A > DimmerState.MaxAlpha)
+                _state.DebugInfo = $"The saved BackgroundHex transparency of {backgroundColor.A} is above the maximum of {DimmerState.MaxAlpha}, using {DimmerState.MaxAlpha} instead";
+
             return backgroundColor;
         }
         catch (Exception ex)
@@ -55,12 +91,129 @@ public class DimmerSettings: INotifyPropertyChanged
         }
     }
 
+    private void WarnAboutTransparency()
+    {
+        var warning = DimmerState.GetTransparencyWarning(_state.SelectedColor);
+        if (warning != null)
+            _state.DebugInfo = warning;
+    }
+
     private string GetSavedSettings()
     {
         var savedSettings = _configuration.AppSettings.Settings.AllKeys.Select(key => $"({key}: {_configuration.AppSettings.Settings[key]})");
         return String.Join(", ", savedSettings);
     }
 
+    public bool GetDimmingEnabledFromSettings()
+    {
+        var fallbackValue = true;
+        try
+        {
+            string? dimmingEnabled = _configuration.AppSettings?.Settings["DimmingEnabled"]?.Value;
+            dimmingEnabled ??= fallbackValue.ToString();
+
+            return bool.Parse(dimmingEnabled);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public string GetExcludedDisplaysFromSettings()
+    {
+        var excludedDisplays = _configuration.AppSettings?.Settings["ExcludedDisplays"]?.Value ?? String.Empty;
+        return MigrateDeviceNamesToScreenIds(excludedDisplays);
+    }
+
+    /// <summary>
+    /// Saves the excluded displays once every device name saved by older versions has been turned into a screen id, so they are only migrated once.
+    /// While a named screen isn't connected the settings are left as they are, so its exclusion isn't lost.
+    /// </summary>
+    private void SaveMigratedExcludedDisplays()
+    {
+        var savedExcludedDisplays = _configuration.AppSettings?.Settings["ExcludedDisplays"]?.Value ?? String.Empty;
+        if (!SplitDisplays(savedExcludedDisplays).Any(IsDeviceName) || SplitDisplays(_state.ExcludedDisplays).Any(IsDeviceName))
+            return;
+
+        try
+        {
+            SaveSetting("ExcludedDisplays", _state.ExcludedDisplays);
+            _configuration.Save(ConfigurationSaveMode.Modified);
+            ConfigurationManager.RefreshSection("appSettings");
+            _state.DebugInfo = $"Saved the excluded displays as screen ids: {_state.ExcludedDisplays}";
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+        }
+    }
+
+    private static string[] SplitDisplays(string displays)
+    {
+        return displays.Split(',', StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries);
+    }
+
+    private static bool IsDeviceName(string display)
+    {
+        return display.StartsWith(@"\\.\", StringComparison.Ordinal);
+    }
+
+    /// <summary>
+    /// Replaces the device names (such as \\.\DISPLAY2) saved by older versions with the id of the screen that currently has that name.
+    /// The device names follow the enumeration order, so matching them on every load would exclude whichever monitor ends up with the name.
+    /// </summary>
+    private string MigrateDeviceNamesToScreenIds(string excludedDisplays)
+    {
+        var migratedDisplays = new List<string>();
+        foreach (var display in SplitDisplays(excludedDisplays))
+        {
+            if (!IsDeviceName(display))
+            {
+                migratedDisplays.Add(display);
+                continue;
+            }
+
+            var screen = Screen.AllScreens.FirstOrDefault(candidate => String.Equals(candidate.DeviceName, display, StringComparison.OrdinalIgnoreCase));
+            if (screen == null)
+            {
+                _state.DebugInfo = $"Keeping the excluded display {display} as it is until a screen with that name is connected to take its id from";
+                migratedDisplays.Add(display);
+            }
+            else
+            {
+                migratedDisplays.Add(WindowsEventsManager.GetScreenId(screen));
+            }
+        }
+        return String.Join(",", migratedDisplays.Distinct(StringComparer.OrdinalIgnoreCase));
+    }
+
+    public string GetDisplayColorsFromSettings()
+    {
+        return _configuration.AppSettings?.Settings["DisplayColors"]?.Value ?? String.Empty;
+    }
+
+    public string GetIgnoredWindowTitlesFromSettings()
+    {
+        return _configuration.AppSettings?.Settings["IgnoredWindowTitles"]?.Value ?? String.Empty;
+    }
+
+    public string GetIgnoredProcessesFromSettings()
+    {
+        return _configuration.AppSettings?.Settings["IgnoredProcesses"]?.Value ?? String.Empty;
+    }
+
+    public string GetPauseHotkeyFromSettings()
+    {
+        return _configuration.AppSettings?.Settings["PauseHotkey"]?.Value ?? String.Empty;
+    }
+
+    public string GetEventLogPathFromSettings()
+    {
+        return _configuration.AppSettings?.Settings["EventLogPath"]?.Value ?? String.Empty;
+    }
+
     public bool GetTopmostFromSettings()
     {
         var fallbackValue = false;
@@ -95,6 +248,176 @@ public class DimmerSettings: INotifyPropertyChanged
         }
     }
 
+    public DimmerPattern GetPatternFromSettings()
+    {
+        var fallbackValue = DimmerPattern.None;
+        try
+        {
+            string? pattern = _configuration.AppSettings?.Settings["Pattern"]?.Value;
+            pattern ??= fallbackValue.ToString();
+
+            return ParseEnumSetting("Pattern", pattern, fallbackValue);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public bool GetExcludeFromCaptureFromSettings()
+    {
+        var fallbackValue = WindowsEventsManager.IsCaptureExclusionSupported;
+        try
+        {
+            string? excludeFromCapture = _configuration.AppSettings?.Settings["ExcludeFromCapture"]?.Value;
+            excludeFromCapture ??= fallbackValue.ToString();
+
+            return bool.Parse(excludeFromCapture);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public bool GetAutoSuspendFullscreenFromSettings()
+    {
+        var fallbackValue = false;
+        try
+        {
+            string? autoSuspendFullscreen = _configuration.AppSettings?.Settings["AutoSuspendFullscreen"]?.Value;
+            autoSuspendFullscreen ??= fallbackValue.ToString();
+
+            return bool.Parse(autoSuspendFullscreen);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public bool GetRespectWorkAreaFromSettings()
+    {
+        var fallbackValue = false;
+        try
+        {
+            string? respectWorkArea = _configuration.AppSettings?.Settings["RespectWorkArea"]?.Value;
+            respectWorkArea ??= fallbackValue.ToString();
+
+            return bool.Parse(respectWorkArea);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public bool GetDeferInitialDimmersFromSettings()
+    {
+        var fallbackValue = false;
+        try
+        {
+            string? deferInitialDimmers = _configuration.AppSettings?.Settings["DeferInitialDimmers"]?.Value;
+            deferInitialDimmers ??= fallbackValue.ToString();
+
+            return bool.Parse(deferInitialDimmers);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public int GetTopmostRefreshSecondsFromSettings()
+    {
+        var fallbackValue = 0;
+        try
+        {
+            string? topmostRefreshSeconds = _configuration.AppSettings?.Settings["TopmostRefreshSeconds"]?.Value;
+            topmostRefreshSeconds ??= fallbackValue.ToString();
+
+            return ClampSetting("TopmostRefreshSeconds", int.Parse(topmostRefreshSeconds), 0, DimmerState.MaxTopmostRefreshSeconds);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public int GetAnimationMillisecondsFromSettings()
+    {
+        var fallbackValue = 0;
+        try
+        {
+            string? animationMilliseconds = _configuration.AppSettings?.Settings["AnimationMilliseconds"]?.Value;
+            animationMilliseconds ??= fallbackValue.ToString();
+
+            return ClampSetting("AnimationMilliseconds", int.Parse(animationMilliseconds), 0, DimmerState.MaxDelayMilliseconds);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public int GetFocusDebounceMillisecondsFromSettings()
+    {
+        var fallbackValue = 0;
+        try
+        {
+            string? focusDebounceMilliseconds = _configuration.AppSettings?.Settings["FocusDebounceMilliseconds"]?.Value;
+            focusDebounceMilliseconds ??= fallbackValue.ToString();
+
+            return ClampSetting("FocusDebounceMilliseconds", int.Parse(focusDebounceMilliseconds), 0, DimmerState.MaxDelayMilliseconds);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public int GetScreenEdgeToleranceFromSettings()
+    {
+        var fallbackValue = 20;
+        try
+        {
+            string? screenEdgeTolerance = _configuration.AppSettings?.Settings["ScreenEdgeTolerance"]?.Value;
+            screenEdgeTolerance ??= fallbackValue.ToString();
+
+            return ClampSetting("ScreenEdgeTolerance", int.Parse(screenEdgeTolerance), 0, DimmerState.MaxScreenEdgeTolerance);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
+    public ActiveScreenSource GetActiveByFromSettings()
+    {
+        var fallbackValue = ActiveScreenSource.KeyboardFocus;
+        try
+        {
+            string? activeBy = _configuration.AppSettings?.Settings["ActiveBy"]?.Value;
+            activeBy ??= fallbackValue.ToString();
+
+            return ParseEnumSetting("ActiveBy", activeBy, fallbackValue);
+        }
+        catch (Exception ex)
+        {
+            _state.DebugInfo = ex.ToString();
+            return fallbackValue;
+        }
+    }
+
     public string CurrentSavedColor => _configuration.AppSettings.Settings["BackgroundHex"] != null?
         $"#{_configuration.AppSettings.Settings["BackgroundHex"].Value}":
         "No saved configuration found";
@@ -104,30 +427,63 @@ public class DimmerSettings: INotifyPropertyChanged
         _state.DebugInfo = "Saving settings";
         try
         {
-
-            if (_configuration.AppSettings.Settings["BackgroundHex"] == null)
-                _configuration.AppSettings.Settings.Add("BackgroundHex", _state.SelectedColor.ToString().Replace("#", String.Empty));
-            else
-                _configuration.AppSettings.Settings["BackgroundHex"].Value = _state.SelectedColor.ToString().Replace("#", String.Empty);
-
-            if (_configuration.AppSettings.Settings["Topmost"] == null)
-                _configuration.AppSettings.Settings.Add("Topmost", _state.Topmost.ToString());
-            else
-                _configuration.AppSettings.Settings["Topmost"].Value = _state.Topmost.ToString();
-
-            if (_configuration.AppSettings.Settings["MinimizeToTray"] == null)
-                _configuration.AppSettings.Settings.Add("MinimizeToTray", _state.MinimizeToTray.ToString());
-            else
-                _configuration.AppSettings.Settings["MinimizeToTray"].Value = _state.MinimizeToTray.ToString();
+            SaveSetting("BackgroundHex", _state.SelectedColor.ToString().Replace("#", String.Empty));
+            SaveSetting("DimmingEnabled", _state.IsDimmingEnabled.ToString());
+            SaveSetting("ExcludedDisplays", _state.ExcludedDisplays);
+            SaveSetting("DisplayColors", _state.DisplayColors);
+            SaveSetting("IgnoredWindowTitles", _state.IgnoredWindowTitles);
+            SaveSetting("IgnoredProcesses", _state.IgnoredProcesses);
+            SaveSetting("PauseHotkey", _state.PauseHotkey);
+            SaveSetting("EventLogPath", _state.EventLogPath);
+            SaveSetting("Topmost", _state.Topmost.ToString());
+            SaveSetting("MinimizeToTray", _state.MinimizeToTray.ToString());
+            SaveSetting("Pattern", _state.Pattern.ToString());
+            SaveSetting("TopmostRefreshSeconds", _state.TopmostRefreshSeconds.ToString());
+            SaveSetting("AnimationMilliseconds", _state.AnimationMilliseconds.ToString());
+            SaveSetting("FocusDebounceMilliseconds", _state.FocusDebounceMilliseconds.ToString());
+            SaveSetting("ScreenEdgeTolerance", _state.ScreenEdgeTolerance.ToString());
+            SaveSetting("ActiveBy", _state.ActiveBy.ToString());
+            SaveSetting("ExcludeFromCapture", _state.ExcludeFromCapture.ToString());
+            SaveSetting("AutoSuspendFullscreen", _state.AutoSuspendFullscreen.ToString());
+            SaveSetting("RespectWorkArea", _state.RespectWorkArea.ToString());
+            SaveSetting("DeferInitialDimmers", _state.DeferInitialDimmers.ToString());
 
             _configuration.Save(ConfigurationSaveMode.Full);
             ConfigurationManager.RefreshSection("appSettings");
 
-            _state.DebugInfo = $"Settings saved successfuly.\r\nSaved color: {_state.SelectedColor}\r\nTopmost: {_state.Topmost}\r\nMinimizeToTray: {_state.MinimizeToTray}";
+            _state.DebugInfo = $"Settings saved successfuly.\r\nSaved color: {_state.SelectedColor}\r\nDimmingEnabled: {_state.IsDimmingEnabled}\r\nExcludedDisplays: {_state.ExcludedDisplays}\r\nDisplayColors: {_state.DisplayColors}\r\nIgnoredWindowTitles: {_state.IgnoredWindowTitles}\r\nIgnoredProcesses: {_state.IgnoredProcesses}\r\nPauseHotkey: {_state.PauseHotkey}\r\nEventLogPath: {_state.EventLogPath}\r\nTopmost: {_state.Topmost}\r\nMinimizeToTray: {_state.MinimizeToTray}\r\nPattern: {_state.Pattern}\r\nTopmostRefreshSeconds: {_state.TopmostRefreshSeconds}\r\nAnimationMilliseconds: {_state.AnimationMilliseconds}\r\nFocusDebounceMilliseconds: {_state.FocusDebounceMilliseconds}\r\nScreenEdgeTolerance: {_state.ScreenEdgeTolerance}\r\nActiveBy: {_state.ActiveBy}\r\nExcludeFromCapture: {_state.ExcludeFromCapture}\r\nAutoSuspendFullscreen: {_state.AutoSuspendFullscreen}\r\nRespectWorkArea: {_state.RespectWorkArea}\r\nDeferInitialDimmers: {_state.DeferInitialDimmers}";
+            WarnAboutTransparency();
         }
         catch (Exception ex)
         {
             _state.DebugInfo = ex.ToString();
         }
     }
+
+    private T ParseEnumSetting<T>(string key, string value, T fallbackValue) where T : struct, Enum
+    {
+        // Enum.TryParse also accepts any number, even one that isn't a defined value
+        if (Enum.TryParse<T>(value, out var parsedValue) && Enum.IsDefined(parsedValue))
+            return parsedValue;
+
+        _state.DebugInfo = $"The saved {key} of {value} is not one of {String.Join(", ", Enum.GetNames<T>())}, using {fallbackValue} instead";
+        return fallbackValue;
+    }
+
+    private int ClampSetting(string key, int value, int min, int max)
+    {
+        var clampedValue = Math.Clamp(value, min, max);
+        if (clampedValue != value)
+            _state.DebugInfo = $"The saved {key} of {value} is outside of the {min} to {max} range, using {clampedValue} instead";
+
+        return clampedValue;
+    }
+
+    private void SaveSetting(string key, string value)
+    {
+        if (_configuration.AppSettings.Settings[key] == null)
+            _configuration.AppSettings.Settings.Add(key, value);
+        else
+            _configuration.AppSettings.Settings[key].Value = value;
+    }
 }
@@ -23,6 +23,212 @@ public class DimmerState : INotifyPropertyChanged
     }
 
 
+    private bool _isDimmingEnabled = true;
+    public bool IsDimmingEnabled
+    {
+        get { return _isDimmingEnabled; }
+        set
+        {
+            _isDimmingEnabled = value;
+            OnPropertyChanged(nameof(IsDimmingEnabled));
+        }
+    }
+
+    private string _eventLogPath = "";
+    /// <summary>
+    /// File where the state changes are appended as JSON lines. Empty disables the event log.
+    /// </summary>
+    public string EventLogPath
+    {
+        get { return _eventLogPath; }
+        set
+        {
+            _eventLogPath = value;
+            OnPropertyChanged(nameof(EventLogPath));
+        }
+    }
+
+    private string _pauseHotkey = "";
+    /// <summary>
+    /// Global shortcut that toggles the dimming, such as Ctrl+Alt+D. Empty disables it.
+    /// </summary>
+    public string PauseHotkey
+    {
+        get { return _pauseHotkey; }
+        set
+        {
+            _pauseHotkey = value;
+            OnPropertyChanged(nameof(PauseHotkey));
+        }
+    }
+
+    private bool _deferInitialDimmers = false;
+    /// <summary>
+    /// Whether the dimmers are only created on startup once another window gets the focus, so launching doesn't flash every screen.
+    /// </summary>
+    public bool DeferInitialDimmers
+    {
+        get { return _deferInitialDimmers; }
+        set
+        {
+            _deferInitialDimmers = value;
+            OnPropertyChanged(nameof(DeferInitialDimmers));
+        }
+    }
+
+    private bool _autoSuspendFullscreen = false;
+    /// <summary>
+    /// Whether all the dimmers are hidden while a fullscreen app, such as a video or a game, has the focus.
+    /// </summary>
+    public bool AutoSuspendFullscreen
+    {
+        get { return _autoSuspendFullscreen; }
+        set
+        {
+            _autoSuspendFullscreen = value;
+            OnPropertyChanged(nameof(AutoSuspendFullscreen));
+        }
+    }
+
+    private bool _respectWorkArea = false;
+    /// <summary>
+    /// Whether the dimmers only cover the working area of each screen, leaving the taskbar undimmed.
+    /// </summary>
+    public bool RespectWorkArea
+    {
+        get { return _respectWorkArea; }
+        set
+        {
+            _respectWorkArea = value;
+            OnPropertyChanged(nameof(RespectWorkArea));
+        }
+    }
+
+    private bool _isFullscreenAppFocused = false;
+    public bool IsFullscreenAppFocused
+    {
+        get { return _isFullscreenAppFocused; }
+        set
+        {
+            // Only notifies actual changes so the dimmers aren't shown or hidden again on every window event
+            if (_isFullscreenAppFocused == value)
+                return;
+            _isFullscreenAppFocused = value;
+            OnPropertyChanged(nameof(IsFullscreenAppFocused));
+        }
+    }
+
+    private string _excludedDisplays = "";
+    /// <summary>
+    /// Comma separated ids of the screens that are never dimmed, as shown on the focused screen id.
+    /// Device names (such as \\.\DISPLAY2) saved by older versions are turned into ids when the settings are loaded.
+    /// </summary>
+    public string ExcludedDisplays
+    {
+        get { return _excludedDisplays; }
+        set
+        {
+            _excludedDisplays = value;
+            OnPropertyChanged(nameof(ExcludedDisplays));
+        }
+    }
+
+    public bool IsDisplayExcluded(string screenId)
+    {
+        if (String.IsNullOrWhiteSpace(ExcludedDisplays))
+            return false;
+
+        var excludedDisplays = ExcludedDisplays.Split(',', StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries);
+        return excludedDisplays.Contains(screenId, StringComparer.OrdinalIgnoreCase);
+    }
+
+    private string _displayColors = "";
+    /// <summary>
+    /// Comma separated screen ids with the color used to dim each of them, such as id=#80102040. Screens that aren't listed use the selected color.
+    /// </summary>
+    public string DisplayColors
+    {
+        get { return _displayColors; }
+        set
+        {
+            _displayColors = value;
+            OnPropertyChanged(nameof(DisplayColors));
+        }
+    }
+
+    /// <summary>
+    /// Gets the brush for the dimmer on the given screen, using the color given to that screen when there is one.
+    /// </summary>
+    public Brush GetBrushForScreen(string screenId)
+    {
+        var displayColor = GetDisplayColor(DisplayColors, screenId);
+        if (displayColor == null)
+            return SelectedBrush;
+
+        var color = displayColor.Value;
+        if (color.A > MaxAlpha)
+            color.A = MaxAlpha;
+        return DimmerPatternBrush.Create(Pattern, color);
+    }
+
+    /// <summary>
+    /// Finds the color given to a screen on a list such as "id=#80102040,otherId=#60000000".
+    /// Each id is split from its color on the last equals sign, and entries whose color isn't in the #AARRGGBB format are ignored.
+    /// </summary>
+    /// <returns>The color given to the screen or null if it has none.</returns>
+    public static Color? GetDisplayColor(string displayColors, string screenId)
+    {
+        foreach (var entry in displayColors.Split(',', StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries))
+        {
+            var separatorIndex = entry.LastIndexOf('=');
+            if (separatorIndex <= 0 || !String.Equals(entry[..separatorIndex].Trim(), screenId, StringComparison.OrdinalIgnoreCase))
+                continue;
+
+            var hex = entry[(separatorIndex + 1)..].Trim().TrimStart('#');
+            if (hex.Length != 8 || !uint.TryParse(hex, System.Globalization.NumberStyles.HexNumber, null, out var argb))
+                continue;
+
+            return Color.FromArgb((byte)(argb >> 24), (byte)(argb >> 16), (byte)(argb >> 8), (byte)argb);
+        }
+        return null;
+    }
+
+    private string _ignoredWindowTitles = "";
+    /// <summary>
+    /// Comma separated parts of window titles, such as a floating mini player, that never move the spotlight when they get the focus.
+    /// </summary>
+    public string IgnoredWindowTitles
+    {
+        get { return _ignoredWindowTitles; }
+        set
+        {
+            _ignoredWindowTitles = value;
+            OnPropertyChanged(nameof(IgnoredWindowTitles));
+        }
+    }
+
+    private string _ignoredProcesses = "";
+    /// <summary>
+    /// Comma separated process names (such as vlc, without the .exe) whose windows never move the spotlight when they get the focus.
+    /// </summary>
+    public string IgnoredProcesses
+    {
+        get { return _ignoredProcesses; }
+        set
+        {
+            _ignoredProcesses = value;
+            OnPropertyChanged(nameof(IgnoredProcesses));
+        }
+    }
+
+    public bool IsWindowIgnored(string title, string processName)
+    {
+        var ignoredWindowTitles = IgnoredWindowTitles.Split(',', StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries);
+        var ignoredProcesses = IgnoredProcesses.Split(',', StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries);
+        return ignoredWindowTitles.Any(ignoredWindowTitle => title.Contains(ignoredWindowTitle, StringComparison.OrdinalIgnoreCase)) ||
+            ignoredProcesses.Contains(processName, StringComparer.OrdinalIgnoreCase);
+    }
+
     private bool _verbose = false;
     public bool Verbose
     {
@@ -57,6 +263,90 @@ public class DimmerState : INotifyPropertyChanged
         }
     }
 
+    private ActiveScreenSource _activeBy = ActiveScreenSource.KeyboardFocus;
+    public ActiveScreenSource ActiveBy
+    {
+        get { return _activeBy; }
+        set
+        {
+            _activeBy = value;
+            OnPropertyChanged(nameof(ActiveBy));
+        }
+    }
+    public ActiveScreenSource[] AvailableActiveScreenSources => Enum.GetValues<ActiveScreenSource>();
+
+    // Upper bounds for the numeric settings, so a typed or hand edited value can't make the dimmers unusable
+    public const int MaxDelayMilliseconds = 5000;
+    public const int MaxScreenEdgeTolerance = 500;
+    public const int MaxTopmostRefreshSeconds = 3600;
+
+    private int _focusDebounceMilliseconds = 0;
+    /// <summary>
+    /// How long the focus has to stay on a screen before the spotlight moves to it. Zero moves it right away.
+    /// </summary>
+    public int FocusDebounceMilliseconds
+    {
+        get { return _focusDebounceMilliseconds; }
+        set
+        {
+            _focusDebounceMilliseconds = Math.Clamp(value, 0, MaxDelayMilliseconds);
+            OnPropertyChanged(nameof(FocusDebounceMilliseconds));
+        }
+    }
+
+    private int _screenEdgeTolerance = 20;
+    /// <summary>
+    /// How many pixels a window can reach past the edge of its screen without the focus moving to the neighbouring screen.
+    /// </summary>
+    public int ScreenEdgeTolerance
+    {
+        get { return _screenEdgeTolerance; }
+        set
+        {
+            _screenEdgeTolerance = Math.Clamp(value, 0, MaxScreenEdgeTolerance);
+            OnPropertyChanged(nameof(ScreenEdgeTolerance));
+        }
+    }
+
+    private int _animationMilliseconds = 0;
+    /// <summary>
+    /// How long the dimmers take to fade in and out when the focus changes. Zero shows and hides them instantly.
+    /// </summary>
+    public int AnimationMilliseconds
+    {
+        get { return _animationMilliseconds; }
+        set
+        {
+            _animationMilliseconds = Math.Clamp(value, 0, MaxDelayMilliseconds);
+            OnPropertyChanged(nameof(AnimationMilliseconds));
+        }
+    }
+
+    private int _topmostRefreshSeconds = 0;
+    /// <summary>
+    /// How often the dimmers are put back on top of other topmost windows. Zero disables the periodic refresh.
+    /// </summary>
+    public int TopmostRefreshSeconds
+    {
+        get { return _topmostRefreshSeconds; }
+        set
+        {
+            _topmostRefreshSeconds = Math.Clamp(value, 0, MaxTopmostRefreshSeconds);
+            OnPropertyChanged(nameof(TopmostRefreshSeconds));
+        }
+    }
+
+    private bool _excludeFromCapture = WindowsEventsManager.IsCaptureExclusionSupported;
+    public bool ExcludeFromCapture
+    {
+        get { return _excludeFromCapture; }
+        set
+        {
+            _excludeFromCapture = value;
+            OnPropertyChanged(nameof(ExcludeFromCapture));
+        }
+    }
+
     private bool _startWithWindows = false;
 
 
@@ -78,14 +368,25 @@ public class DimmerState : INotifyPropertyChanged
         get => _focusedScreen;
         set
         {
+            var isNewScreen = !value.Equals(_focusedScreen);
             _focusedScreen = value;
             OnPropertyChanged(nameof(FocusedScreen));
+            if (!isNewScreen)
+                return;
+
+            // Looking the id up queries the display devices, so it's only done again when the focus moves to another screen
+            _focusedScreenId = null;
             OnPropertyChanged(nameof(FocusedScreenName));
+            OnPropertyChanged(nameof(FocusedScreenId));
         }
     }
     public string FocusedScreenName => FocusedScreen.DeviceName;
+    private string? _focusedScreenId;
+    public string FocusedScreenId => _focusedScreenId ??= WindowsEventsManager.GetScreenId(FocusedScreen);
 
     private bool _isDebugInfoVisible;
+    // Keeps the dimmed screens from going fully dark
+    public const byte MaxAlpha = 225;
     private Color? _selectedColor;
     public Color SelectedColor
     {
@@ -95,16 +396,66 @@ public class DimmerState : INotifyPropertyChanged
         }
         set
         {
-            if (value.A > 225)
-                value.A = 225;
+            if (value.A > MaxAlpha)
+                value.A = MaxAlpha;
             _selectedColor = value;
             OnPropertyChanged(nameof(SelectedColor));
             OnPropertyChanged(nameof(SelectedBrush));
+            OnPropertyChanged(nameof(ColorPreview));
+        }
+    }
+    private DimmerPattern _pattern = DimmerPattern.None;
+    public DimmerPattern Pattern
+    {
+        get { return _pattern; }
+        set
+        {
+            _pattern = value;
+            OnPropertyChanged(nameof(Pattern));
+            OnPropertyChanged(nameof(SelectedBrush));
+        }
+    }
+    public DimmerPattern[] AvailablePatterns => Enum.GetValues<DimmerPattern>();
+
+    /// <summary>
+    /// Gets a warning for colors that make the dimmers look broken, either by not dimming at all or by hiding the screen contents.
+    /// </summary>
+    /// <returns>The warning message or null if the color is fine.</returns>
+    public static string? GetTransparencyWarning(Color color)
+    {
+        if (color.A == 0)
+            return "Warning: the dimming color is fully transparent, so the dimmers will be invisible.";
+        if (color.A >= MaxAlpha)
+            return "Warning: the dimming color is at its maximum opacity, so the dimmed screens will be barely visible.";
+        return null;
+    }
+
+    /// <summary>
+    /// Computes the color seen where a dimmer of the given color covers the given background.
+    /// </summary>
+    public static Color CompositeOver(Color color, Color background)
+    {
+        byte Blend(byte channel, byte backgroundChannel) => (byte)Math.Round((channel * color.A + backgroundChannel * (255 - color.A)) / 255.0);
+        return Color.FromRgb(Blend(color.R, background.R), Blend(color.G, background.G), Blend(color.B, background.B));
+    }
+
+    /// <summary>
+    /// Describes how white, gray and black contents look once dimmed with the selected color, as it's hard to tell from the transparency alone.
+    /// </summary>
+    public string ColorPreview
+    {
+        get
+        {
+            var white = CompositeOver(SelectedColor, Colors.White);
+            var gray = CompositeOver(SelectedColor, Color.FromRgb(128, 128, 128));
+            var black = CompositeOver(SelectedColor, Colors.Black);
+            return $"Dimmed white: {white}, gray: {gray}, black: {black}";
         }
     }
+
     public Brush SelectedBrush
     {
-        get { return new SolidColorBrush(SelectedColor); }
+        get { return DimmerPatternBrush.Create(Pattern, SelectedColor); }
     }
     public bool IsDebugInfoVisible
     {
@@ -117,6 +468,17 @@ public class DimmerState : INotifyPropertyChanged
     }
     public Visibility DebugInfoVisibility => IsDebugInfoVisible ? Visibility.Visible : Visibility.Collapsed;
 
+    // Enough to see what led to a wrong spotlight without keeping every focus change of a long session
+    public const int MaxFocusHistoryEntries = 20;
+    private readonly FocusHistory _focusHistory = new(MaxFocusHistoryEntries);
+    public string FocusHistory => _focusHistory.ToString();
+
+    public void AddToFocusHistory(FocusHistoryEntry entry)
+    {
+        _focusHistory.Add(entry);
+        OnPropertyChanged(nameof(FocusHistory));
+    }
+
     private ActiveWindowInfo? _activeWindowInfo;
     public ActiveWindowInfo ActiveWindowInfo
     {
@@ -0,0 +1,51 @@
+﻿namespace SpotlightDimmer.Models;
+
+/// <summary>
+/// Keeps the last focus changes, so a spotlight that jumps to the wrong screen can be traced back to the windows that were focused before it.
+/// Once full, every new entry replaces the oldest one.
+/// </summary>
+public class FocusHistory
+{
+    private readonly FocusHistoryEntry[] _entries;
+    private int _nextIndex;
+    private int _count;
+
+    public FocusHistory(int capacity)
+    {
+        _entries = new FocusHistoryEntry[capacity];
+    }
+
+    public void Add(FocusHistoryEntry entry)
+    {
+        _entries[_nextIndex] = entry;
+        _nextIndex = (_nextIndex + 1) % _entries.Length;
+        _count = Math.Min(_count + 1, _entries.Length);
+    }
+
+    /// <summary>
+    /// Gets the kept entries from the oldest to the newest.
+    /// </summary>
+    public List<FocusHistoryEntry> GetEntries()
+    {
+        var oldestIndex = (_nextIndex - _count + _entries.Length) % _entries.Length;
+        return Enumerable.Range(0, _count)
+            .Select(offset => _entries[(oldestIndex + offset) % _entries.Length])
+            .ToList();
+    }
+
+    /// <summary>
+    /// Lists the entries from the newest to the oldest, as shown on the debug panel.
+    /// </summary>
+    public override string ToString()
+    {
+        return String.Join("\r\n", GetEntries().AsEnumerable().Reverse());
+    }
+}
+
+public record FocusHistoryEntry(DateTimeOffset Timestamp, string Title, string ProcessName, string ScreenId)
+{
+    public override string ToString()
+    {
+        return $"{Timestamp.ToString("HH:mm:ss.fff", System.Globalization.CultureInfo.InvariantCulture)} {Title} ({ProcessName}) on {ScreenId}";
+    }
+}
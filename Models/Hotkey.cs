@@ -0,0 +1,62 @@
+﻿namespace SpotlightDimmer.Models;
+
+/// <summary>
+/// A global keyboard shortcut in the format expected by RegisterHotKey.
+/// </summary>
+/// <param name="Modifiers">The MOD_* flags of the modifier keys.</param>
+/// <param name="VirtualKey">The virtual key code of the main key.</param>
+public record Hotkey(uint Modifiers, uint VirtualKey)
+{
+    public const uint MOD_ALT = 0x0001;
+    public const uint MOD_CONTROL = 0x0002;
+    public const uint MOD_SHIFT = 0x0004;
+    public const uint MOD_WIN = 0x0008;
+    // Keeps a held down shortcut from firing repeatedly
+    public const uint MOD_NOREPEAT = 0x4000;
+
+    /// <summary>
+    /// Parses a shortcut such as "Ctrl+Alt+D" or "Ctrl+Shift+F12": any number of modifiers (Ctrl, Alt, Shift, Win) followed by a single key.
+    /// Only the function keys can be used without a modifier, as any other key would stop reaching the other programs.
+    /// </summary>
+    /// <returns>False if the text has no key, an unknown key, a modifier used as the key or no modifier before a key other than F1 to F24.</returns>
+    public static bool TryParse(string text, out Hotkey? hotkey)
+    {
+        hotkey = null;
+        var parts = text.Split('+', StringSplitOptions.TrimEntries);
+        if (parts.Any(part => part.Length == 0))
+            return false;
+
+        uint modifiers = 0;
+        foreach (var part in parts.SkipLast(1))
+        {
+            var modifier = part.ToLowerInvariant() switch
+            {
+                "ctrl" or "control" => MOD_CONTROL,
+                "alt" => MOD_ALT,
+                "shift" => MOD_SHIFT,
+                "win" or "windows" => MOD_WIN,
+                _ => 0u
+            };
+            if (modifier == 0)
+                return false;
+            modifiers |= modifier;
+        }
+
+        var keyName = parts.Last();
+        // Digits are named D0 to D9 on the Keys enum
+        if (keyName.Length == 1 && char.IsDigit(keyName[0]))
+            keyName = $"D{keyName}";
+        // Enum.TryParse also accepts raw numbers and comma separated lists of names, which aren't key names
+        if (int.TryParse(keyName, out _) || keyName.Contains(',') || !Enum.TryParse<Keys>(keyName, true, out var key))
+            return false;
+        if (key == Keys.None || (key & ~Keys.KeyCode) != 0)
+            return false;
+        if (key is Keys.ControlKey or Keys.LControlKey or Keys.RControlKey or Keys.Menu or Keys.LMenu or Keys.RMenu or Keys.ShiftKey or Keys.LShiftKey or Keys.RShiftKey or Keys.LWin or Keys.RWin)
+            return false;
+        if (modifiers == 0 && key is not (>= Keys.F1 and <= Keys.F24))
+            return false;
+
+        hotkey = new Hotkey(modifiers, (uint)key);
+        return true;
+    }
+}
@@ -1,17 +1,42 @@
-﻿namespace SpotlightDimmer.Models;
+﻿using System.Diagnostics;
+using System.Windows.Threading;
+
+namespace SpotlightDimmer.Models;
 
 public class WindowsEventsManager : IDisposable
 {
     private const int NChars = 256;
+    // Larger than any real display (16K is 15360 pixels wide), so bigger sizes can only come from a driver glitch
+    private const int MaxSaneScreenDimension = 16384;
     private const uint EVENT_SYSTEM_FOREGROUND = 0x0003;
+    // Windows 10 version 2004 is the first to support excluding windows from capture
+    private const int MinCaptureExclusionBuild = 19041;
 
     private readonly string[] _ignoredWindows;
+    // The desktop windows cover the whole screen without a caption but aren't fullscreen apps
+    private readonly string[] _desktopWindowClasses = new string[] { "Progman", "WorkerW" };
     private readonly DimmerState _state;
 
     private readonly IntPtr _windowsFocusHook;
     private readonly IntPtr _windowsResizedHook;
     private readonly WinEventDelegate _winEventDelegate;
 
+    private static readonly TimeSpan CursorPollInterval = TimeSpan.FromMilliseconds(100);
+    private readonly DispatcherTimer _cursorTimer;
+    private string? _cursorScreenDeviceName;
+    private string? _focusedWindowScreenDeviceName;
+
+    private uint _lastProcessId;
+    private string _lastProcessName = String.Empty;
+
+    private readonly DispatcherTimer _focusDebounceTimer;
+    private Screen? _pendingFocusedScreen;
+
+    /// <summary>
+    /// Raised whenever another window is brought to the foreground, including the ones that don't move the spotlight.
+    /// </summary>
+    public event EventHandler? ForegroundChanged;
+
     // Methods to get focus events
     private delegate void WinEventDelegate(IntPtr hWinEventHook, uint eventType, IntPtr hwnd, int idObject, int idChild, uint dwEventThread, uint dwmsEventTime);
 
@@ -24,11 +49,43 @@ public class WindowsEventsManager : IDisposable
     [DllImport("user32.dll")]
     [return: MarshalAs(UnmanagedType.Bool)]
     private static extern bool GetWindowRect(IntPtr hWnd, ref RECT lpRect);
+    [DllImport("user32.dll")]
+    private static extern uint GetWindowThreadProcessId(IntPtr hWnd, out uint lpdwProcessId);
+    [DllImport("user32.dll", CharSet = CharSet.Unicode)]
+    private static extern int GetClassName(IntPtr hWnd, StringBuilder lpClassName, int nMaxCount);
+    [DllImport("user32.dll")]
+    private static extern int GetWindowLong(IntPtr hWnd, int nIndex);
+
+    // Used to tell decorated windows apart from fullscreen ones
+    private const int GWL_STYLE = -16;
+    private const int WS_CAPTION = 0x00C00000;
 
     // Methods to get resize events
     private const uint WINEVENT_OUTOFCONTEXT = 0x0000; // Events are ASYNC
     private const uint EVENT_OBJECT_LOCATIONCHANGE = 0x800B;
 
+    // Methods to get the monitor behind a screen
+    private const uint EDD_GET_DEVICE_INTERFACE_NAME = 0x00000001;
+
+    [StructLayout(LayoutKind.Sequential, CharSet = CharSet.Unicode)]
+    private struct DISPLAY_DEVICE
+    {
+        public int cb;
+        [MarshalAs(UnmanagedType.ByValTStr, SizeConst = 32)]
+        public string DeviceName;
+        [MarshalAs(UnmanagedType.ByValTStr, SizeConst = 128)]
+        public string DeviceString;
+        public int StateFlags;
+        [MarshalAs(UnmanagedType.ByValTStr, SizeConst = 128)]
+        public string DeviceID;
+        [MarshalAs(UnmanagedType.ByValTStr, SizeConst = 128)]
+        public string DeviceKey;
+    }
+
+    [DllImport("user32.dll", CharSet = CharSet.Unicode)]
+    [return: MarshalAs(UnmanagedType.Bool)]
+    private static extern bool EnumDisplayDevices(string lpDevice, uint iDevNum, ref DISPLAY_DEVICE lpDisplayDevice, uint dwFlags);
+
     [DllImport("user32.dll")]
     private static extern IntPtr GetForegroundWindow();
     [DllImport("user32.dll")]
@@ -46,11 +103,83 @@ public class WindowsEventsManager : IDisposable
         _winEventDelegate = new WinEventDelegate(WinEventProc);
         _windowsFocusHook = SetWinEventHook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND, IntPtr.Zero, _winEventDelegate, 0, 0, 0);
         _windowsResizedHook = SetWinEventHook(EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_LOCATIONCHANGE, IntPtr.Zero, _winEventDelegate, 0, 0, WINEVENT_OUTOFCONTEXT);
+
+        _focusDebounceTimer = new DispatcherTimer();
+        _focusDebounceTimer.Tick += FocusDebounceTimer_Tick;
+
+        // There's no event for the cursor moving between screens, so its position is polled
+        _cursorTimer = new DispatcherTimer { Interval = CursorPollInterval };
+        _cursorTimer.Tick += CursorTimer_Tick;
+        UpdateCursorTimer();
+        _state.PropertyChanged += (object? sender, PropertyChangedEventArgs e) =>
+        {
+            if (e.PropertyName == nameof(_state.ActiveBy))
+                UpdateCursorTimer();
+        };
+    }
+
+    /// <summary>
+    /// Only polls the cursor while the active screen can follow it.
+    /// </summary>
+    private void UpdateCursorTimer()
+    {
+        // Forget the last screens so the new source takes effect on the next tick or window event
+        _cursorScreenDeviceName = null;
+        _focusedWindowScreenDeviceName = null;
+
+        if (_state.ActiveBy == ActiveScreenSource.KeyboardFocus)
+            _cursorTimer.Stop();
+        else
+            _cursorTimer.Start();
+    }
+
+    private void CursorTimer_Tick(object? sender, EventArgs e)
+    {
+        var cursorScreen = Screen.FromPoint(System.Windows.Forms.Cursor.Position);
+        if (cursorScreen.DeviceName == _cursorScreenDeviceName)
+            return;
+
+        _cursorScreenDeviceName = cursorScreen.DeviceName;
+        if (_state.Verbose)
+            _state.DebugInfo = $"Cursor moved to screen {cursorScreen.DeviceName}";
+        SetFocusedScreen(cursorScreen);
+    }
+
+    /// <summary>
+    /// Moves the spotlight to the screen once it has stayed there for the configured debounce time, so quickly going through windows doesn't flash every dimmer.
+    /// A newer screen replaces the pending one and restarts the wait.
+    /// </summary>
+    private void SetFocusedScreen(Screen screen)
+    {
+        _focusDebounceTimer.Stop();
+        if (_state.FocusDebounceMilliseconds <= 0)
+        {
+            _pendingFocusedScreen = null;
+            _state.FocusedScreen = screen;
+            return;
+        }
+
+        _pendingFocusedScreen = screen;
+        _focusDebounceTimer.Interval = TimeSpan.FromMilliseconds(_state.FocusDebounceMilliseconds);
+        _focusDebounceTimer.Start();
+    }
+
+    private void FocusDebounceTimer_Tick(object? sender, EventArgs e)
+    {
+        _focusDebounceTimer.Stop();
+        if (_pendingFocusedScreen == null)
+            return;
+
+        _state.FocusedScreen = _pendingFocusedScreen;
+        _pendingFocusedScreen = null;
     }
 
 
     private void WinEventProc(IntPtr hWinEventHook, uint eventType, IntPtr hwnd, int idObject, int idChild, uint dwEventThread, uint dwmsEventTime)
     {
+        if (eventType == EVENT_SYSTEM_FOREGROUND)
+            ForegroundChanged?.Invoke(this, EventArgs.Empty);
+
         var stringBuilder = new StringBuilder(NChars);
 
         // get the title of the window
@@ -81,6 +210,8 @@ public class WindowsEventsManager : IDisposable
         var rect = new RECT();
         GetWindowRect(hwnd, ref rect);
 
+        if (eventType == EVENT_SYSTEM_FOREGROUND)
+            AddToFocusHistory(hwnd, title, rect);
 
         if (_state.ActiveWindowInfo.Title == title &&
             rect.left == _state.ActiveWindowInfo.BoundsRectangle.left &&
@@ -89,11 +220,85 @@ public class WindowsEventsManager : IDisposable
             rect.bottom == _state.ActiveWindowInfo.BoundsRectangle.bottom
            )
             return;
+
+        // The process is only looked up when there are processes to ignore
+        var processName = String.IsNullOrWhiteSpace(_state.IgnoredProcesses) ? String.Empty : GetProcessName(hwnd);
+        if (_state.IsWindowIgnored(title, processName))
+        {
+            if (_state.Verbose)
+                _state.DebugInfo = $"Skipping window {title} ignored by the settings";
+            return;
+        }
+
         _state.ActiveWindowInfo = new ActiveWindowInfo(title, rect);
         _state.DebugInfo = $"Activating {title} on hwnd {hwnd} and event {eventType}";
+        _state.IsFullscreenAppFocused = IsFullscreenWindow(hwnd, rect);
+
+        // When following the mouse the focused window only updates the active window info
+        if (_state.ActiveBy == ActiveScreenSource.MousePosition)
+            return;
+
+        // Shrinks the screens so a window that slightly overflows its own screen doesn't count as being on the neighbouring one
+        var activeScreen = GetIntersectingScreen(rect, -_state.ScreenEdgeTolerance);
+
+        // When following either, moving or resizing the focused window within its screen must not take the spotlight back from the cursor
+        var isSameScreen = activeScreen.DeviceName == _focusedWindowScreenDeviceName;
+        _focusedWindowScreenDeviceName = activeScreen.DeviceName;
+        if (_state.ActiveBy == ActiveScreenSource.Either && isSameScreen)
+            return;
+
+        SetFocusedScreen(activeScreen);
+    }
+
+    /// <summary>
+    /// Records every window brought to the foreground, including the ignored ones, along with the screen it's on.
+    /// </summary>
+    private void AddToFocusHistory(IntPtr hwnd, string title, RECT rect)
+    {
+        var screen = GetIntersectingScreen(rect, -_state.ScreenEdgeTolerance);
+        _state.AddToFocusHistory(new FocusHistoryEntry(DateTimeOffset.Now, title, GetProcessName(hwnd), GetScreenId(screen)));
+    }
+
+    /// <summary>
+    /// Gets the name of the process that owns the window.
+    /// The last name is kept, since moving or resizing a window raises an event for every step.
+    /// </summary>
+    private string GetProcessName(IntPtr hwnd)
+    {
+        GetWindowThreadProcessId(hwnd, out var processId);
+        if (processId == _lastProcessId)
+            return _lastProcessName;
+
+        try
+        {
+            using var process = Process.GetProcessById((int)processId);
+            _lastProcessId = processId;
+            _lastProcessName = process.ProcessName;
+            return _lastProcessName;
+        }
+        catch (Exception)
+        {
+            // The process may have exited since the event was raised
+            return String.Empty;
+        }
+    }
+
+    /// <summary>
+    /// Checks if the window is a fullscreen app, such as a video player or a game: it covers its whole screen and has no caption.
+    /// Maximized windows keep their caption, so they aren't considered fullscreen.
+    /// </summary>
+    private bool IsFullscreenWindow(IntPtr hwnd, RECT rect)
+    {
+        var className = new StringBuilder(NChars);
+        GetClassName(hwnd, className, NChars);
+        if (_desktopWindowClasses.Contains(className.ToString()))
+            return false;
+
+        if ((GetWindowLong(hwnd, GWL_STYLE) & WS_CAPTION) == WS_CAPTION)
+            return false;
 
-        var activeScreen = GetIntersectingScreen(rect, -20);
-        _state.FocusedScreen = activeScreen;
+        var windowBounds = Rectangle.FromLTRB(rect.left, rect.top, rect.right, rect.bottom);
+        return windowBounds.Contains(Screen.FromRectangle(windowBounds).Bounds);
     }
 
     public static List<Screen> GetNonIntersectingScreens(RECT rect, int sensitivity)
@@ -121,6 +326,84 @@ public class WindowsEventsManager : IDisposable
         return nonIntersectingScreens;
     }
 
+    /// <summary>
+    /// Gets the screens that should receive their own dimmer window, keeping only the first of the screens that mirror each other.
+    /// </summary>
+    public static List<Screen> GetLogicalScreens()
+    {
+        var screens = Screen.AllScreens;
+        return GetLogicalBounds(screens.Select(screen => screen.Bounds))
+            .Select(bounds => screens.First(screen => screen.Bounds == bounds))
+            .ToList();
+    }
+
+    /// <summary>
+    /// Collapses the bounds of mirrored screens into a single logical screen.
+    /// Mirrored (cloned) displays are reported as separate screens that share the exact same bounds, so any bounds
+    /// whose left, top, width and height match an already listed one are treated as the same logical screen and skipped.
+    /// </summary>
+    public static List<Rectangle> GetLogicalBounds(IEnumerable<Rectangle> screenBounds)
+    {
+        return screenBounds
+            .Distinct()
+            .ToList();
+    }
+
+    /// <summary>
+    /// Gets an id for the monitor shown on the screen that doesn't change when other monitors are plugged, unplugged or reordered.
+    /// The device names (such as \\.\DISPLAY2) follow the enumeration order, while the monitor device interface name is built from the
+    /// EDID manufacturer and product codes plus the connection the monitor is plugged into, so it does change when the monitor is moved to another port.
+    /// </summary>
+    /// <returns>The monitor device interface name, or the screen device name if Windows doesn't report one.</returns>
+    public static string GetScreenId(Screen screen)
+    {
+        var displayDevice = new DISPLAY_DEVICE();
+        displayDevice.cb = Marshal.SizeOf(displayDevice);
+
+        if (EnumDisplayDevices(screen.DeviceName, 0, ref displayDevice, EDD_GET_DEVICE_INTERFACE_NAME) && !String.IsNullOrEmpty(displayDevice.DeviceID))
+            return displayDevice.DeviceID;
+
+        return screen.DeviceName;
+    }
+
+    /// <summary>
+    /// Describes the screen with everything needed to tell it apart from the others when configuring the dimmers.
+    /// </summary>
+    public static string DescribeScreen(Screen screen)
+    {
+        var primary = screen.Primary ? " (primary)" : String.Empty;
+        return $"{screen.DeviceName}{primary} at {screen.Bounds.Left},{screen.Bounds.Top} with {screen.Bounds.Width}x{screen.Bounds.Height}\r\nId: {GetScreenId(screen)}";
+    }
+
+    /// <summary>
+    /// Checks if windows can be hidden from screenshots and screen recordings.
+    /// Older versions don't refuse the request but show the window as a black rectangle on captures instead, so it must not be made there.
+    /// </summary>
+    public static bool IsCaptureExclusionSupported => Environment.OSVersion.Version.Build >= MinCaptureExclusionBuild;
+
+    /// <summary>
+    /// Checks if the screen reports a size that a dimmer window can safely cover.
+    /// Displays can report absurd sizes while they're being reconfigured, and creating a window that big can fail or hang the compositor.
+    /// </summary>
+    public static bool HasSaneBounds(Screen screen)
+    {
+        return HasSaneBounds(screen.Bounds.Width, screen.Bounds.Height);
+    }
+
+    public static bool HasSaneBounds(int width, int height)
+    {
+        return width > 0 && width <= MaxSaneScreenDimension &&
+            height > 0 && height <= MaxSaneScreenDimension;
+    }
+
+    /// <summary>
+    /// Checks if both screens are the same logical screen, either by being the same device or by mirroring each other.
+    /// </summary>
+    public static bool IsSameLogicalScreen(Screen screen, Screen otherScreen)
+    {
+        return screen.DeviceName == otherScreen.DeviceName || screen.Bounds == otherScreen.Bounds;
+    }
+
     public static Screen GetIntersectingScreen(RECT rect, int sensitivity)
     {
         foreach (var screen in Screen.AllScreens)
@@ -147,6 +430,8 @@ public class WindowsEventsManager : IDisposable
 
     public void Dispose()
     {
+        _cursorTimer.Stop();
+        _focusDebounceTimer.Stop();
         UnhookWinEvent(_windowsFocusHook);
         UnhookWinEvent(_windowsResizedHook);
     }
@@ -0,0 +1,93 @@
+﻿using SpotlightDimmer.Models;
+using System.Windows.Media;
+using Xunit;
+using Color = System.Windows.Media.Color;
+
+namespace SpotlightDimmer.Tests;
+
+public class DimmerStateTests
+{
+    [Fact]
+    public void WarnsAboutFullyTransparentColors()
+    {
+        var warning = DimmerState.GetTransparencyWarning(Color.FromArgb(0, 0, 0, 0));
+
+        Assert.NotNull(warning);
+        Assert.Contains("fully transparent", warning);
+    }
+
+    [Theory]
+    [InlineData(DimmerState.MaxAlpha)]
+    [InlineData(byte.MaxValue)]
+    public void WarnsAboutColorsAtTheMaximumOpacity(byte alpha)
+    {
+        var warning = DimmerState.GetTransparencyWarning(Color.FromArgb(alpha, 0, 0, 0));
+
+        Assert.NotNull(warning);
+        Assert.Contains("maximum opacity", warning);
+    }
+
+    [Theory]
+    [InlineData((byte)1)]
+    [InlineData((byte)160)]
+    [InlineData((byte)(DimmerState.MaxAlpha - 1))]
+    public void DoesNotWarnAboutPartiallyTransparentColors(byte alpha)
+    {
+        Assert.Null(DimmerState.GetTransparencyWarning(Color.FromArgb(alpha, 0, 0, 0)));
+    }
+
+    [Fact]
+    public void CompositesHalfTransparentBlackOverWhite()
+    {
+        var dimmed = DimmerState.CompositeOver(Color.FromArgb(128, 0, 0, 0), Colors.White);
+
+        Assert.Equal(Color.FromRgb(127, 127, 127), dimmed);
+    }
+
+    [Fact]
+    public void CompositesTransparentAndOpaqueColors()
+    {
+        var background = Color.FromRgb(10, 200, 30);
+        var color = Color.FromRgb(90, 60, 250);
+
+        Assert.Equal(background, DimmerState.CompositeOver(Color.FromArgb(0, 90, 60, 250), background));
+        Assert.Equal(color, DimmerState.CompositeOver(color, background));
+    }
+
+    [Fact]
+    public void PreviewsHowWhiteGrayAndBlackLookOnceDimmed()
+    {
+        var state = new DimmerState { SelectedColor = Color.FromArgb(128, 0, 0, 0) };
+
+        Assert.Equal("Dimmed white: #FF7F7F7F, gray: #FF404040, black: #FF000000", state.ColorPreview);
+    }
+
+    [Fact]
+    public void FindsTheColorGivenToAScreen()
+    {
+        var displayColors = @"\\?\DISPLAY#GSM5B08#1=#80102040, \\?\DISPLAY#DEL40F4#2 = 60000000";
+
+        Assert.Equal(Color.FromArgb(0x80, 0x10, 0x20, 0x40), DimmerState.GetDisplayColor(displayColors, @"\\?\DISPLAY#GSM5B08#1"));
+        Assert.Equal(Color.FromArgb(0x60, 0, 0, 0), DimmerState.GetDisplayColor(displayColors, @"\\?\display#del40f4#2"));
+    }
+
+    [Theory]
+    [InlineData("")]
+    [InlineData("other=#80102040")]
+    [InlineData("screen=#102040")]
+    [InlineData("screen=#NOTACOLOR")]
+    [InlineData("=#80102040")]
+    public void FallsBackWhenTheScreenHasNoValidColor(string displayColors)
+    {
+        Assert.Null(DimmerState.GetDisplayColor(displayColors, "screen"));
+    }
+
+    [Fact]
+    public void UsesTheSelectedColorForScreensWithoutTheirOwn()
+    {
+        var state = new DimmerState { SelectedColor = Color.FromArgb(128, 0, 0, 0), DisplayColors = "other=#80102040" };
+
+        var brush = Assert.IsType<SolidColorBrush>(state.GetBrushForScreen("screen"));
+        Assert.Equal(state.SelectedColor, brush.Color);
+    }
+}
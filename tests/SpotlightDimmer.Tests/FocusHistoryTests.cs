@@ -0,0 +1,51 @@
+﻿using SpotlightDimmer.Models;
+using Xunit;
+
+namespace SpotlightDimmer.Tests;
+
+public class FocusHistoryTests
+{
+    private static FocusHistoryEntry CreateEntry(string title)
+    {
+        return new FocusHistoryEntry(DateTimeOffset.Now, title, "process", "screen");
+    }
+
+    [Fact]
+    public void KeepsTheEntriesFromTheOldestToTheNewest()
+    {
+        var history = new FocusHistory(3);
+        history.Add(CreateEntry("first"));
+        history.Add(CreateEntry("second"));
+
+        Assert.Equal(new[] { "first", "second" }, history.GetEntries().Select(entry => entry.Title));
+    }
+
+    [Fact]
+    public void ReplacesTheOldestEntriesOnceFull()
+    {
+        var history = new FocusHistory(3);
+        foreach (var title in new[] { "first", "second", "third", "fourth", "fifth" })
+            history.Add(CreateEntry(title));
+
+        Assert.Equal(new[] { "third", "fourth", "fifth" }, history.GetEntries().Select(entry => entry.Title));
+    }
+
+    [Fact]
+    public void ListsTheNewestEntryFirst()
+    {
+        var history = new FocusHistory(3);
+        history.Add(new FocusHistoryEntry(new DateTimeOffset(2024, 1, 1, 9, 30, 0, 250, TimeSpan.Zero), "Editor", "code", "screen-1"));
+        history.Add(new FocusHistoryEntry(new DateTimeOffset(2024, 1, 1, 9, 30, 5, 0, TimeSpan.Zero), "Browser", "firefox", "screen-2"));
+
+        Assert.Equal("09:30:05.000 Browser (firefox) on screen-2\r\n09:30:00.250 Editor (code) on screen-1", history.ToString());
+    }
+
+    [Fact]
+    public void IsEmptyBeforeAnyFocusChange()
+    {
+        var history = new FocusHistory(3);
+
+        Assert.Empty(history.GetEntries());
+        Assert.Equal(String.Empty, history.ToString());
+    }
+}
@@ -0,0 +1,51 @@
+﻿using SpotlightDimmer.Models;
+using Xunit;
+
+namespace SpotlightDimmer.Tests;
+
+public class HotkeyTests
+{
+    [Fact]
+    public void ParsesModifiersFollowedByAKey()
+    {
+        Assert.True(Hotkey.TryParse("Ctrl+Shift+F12", out var hotkey));
+        Assert.Equal(new Hotkey(Hotkey.MOD_CONTROL | Hotkey.MOD_SHIFT, (uint)Keys.F12), hotkey);
+    }
+
+    [Theory]
+    [InlineData("ctrl + alt + d", Hotkey.MOD_CONTROL | Hotkey.MOD_ALT, (uint)Keys.D)]
+    [InlineData("Win+1", Hotkey.MOD_WIN, (uint)Keys.D1)]
+    [InlineData("F9", 0u, (uint)Keys.F9)]
+    [InlineData("F24", 0u, (uint)Keys.F24)]
+    public void ParsesKeyNamesAndFunctionKeysOnTheirOwn(string text, uint modifiers, uint virtualKey)
+    {
+        Assert.True(Hotkey.TryParse(text, out var hotkey));
+        Assert.Equal(new Hotkey(modifiers, virtualKey), hotkey);
+    }
+
+    [Theory]
+    [InlineData("")]
+    [InlineData("Ctrl+")]
+    [InlineData("Ctrl+Alt")]
+    [InlineData("Ctrl+Shift")]
+    [InlineData("Ctrl+NotAKey")]
+    [InlineData("Ctrl+65")]
+    [InlineData("Ctrl+A,B")]
+    [InlineData("Ctrl+Alt+Delete+D")]
+    [InlineData("Hyper+D")]
+    public void RejectsInvalidKeys(string text)
+    {
+        Assert.False(Hotkey.TryParse(text, out var hotkey));
+        Assert.Null(hotkey);
+    }
+
+    [Theory]
+    [InlineData("D")]
+    [InlineData("1")]
+    [InlineData("Space")]
+    [InlineData("Escape")]
+    public void RejectsBareKeysOtherThanFunctionKeys(string text)
+    {
+        Assert.False(Hotkey.TryParse(text, out _));
+    }
+}
@@ -0,0 +1,52 @@
+﻿using SpotlightDimmer.Models;
+using Xunit;
+
+namespace SpotlightDimmer.Tests;
+
+public class WindowsEventsManagerTests
+{
+    [Fact]
+    public void CollapsesMirroredScreensIntoTheFirstOne()
+    {
+        var primary = new Rectangle(0, 0, 1920, 1080);
+        var mirror = new Rectangle(0, 0, 1920, 1080);
+        var extended = new Rectangle(1920, 0, 2560, 1440);
+
+        var logicalBounds = WindowsEventsManager.GetLogicalBounds(new[] { primary, extended, mirror });
+
+        Assert.Equal(new[] { primary, extended }, logicalBounds);
+    }
+
+    [Fact]
+    public void KeepsScreensThatOnlyOverlapOrShareASize()
+    {
+        var primary = new Rectangle(0, 0, 1920, 1080);
+        var sameSize = new Rectangle(1920, 0, 1920, 1080);
+        var overlapping = new Rectangle(0, 0, 1280, 720);
+
+        var logicalBounds = WindowsEventsManager.GetLogicalBounds(new[] { primary, sameSize, overlapping });
+
+        Assert.Equal(new[] { primary, sameSize, overlapping }, logicalBounds);
+    }
+
+    [Theory]
+    [InlineData(1920, 1080)]
+    [InlineData(1, 1)]
+    [InlineData(16384, 16384)]
+    public void AcceptsUsualScreenSizes(int width, int height)
+    {
+        Assert.True(WindowsEventsManager.HasSaneBounds(width, height));
+    }
+
+    [Theory]
+    [InlineData(0, 1080)]
+    [InlineData(1920, 0)]
+    [InlineData(-1920, 1080)]
+    [InlineData(16385, 1080)]
+    [InlineData(1920, 16385)]
+    [InlineData(int.MaxValue, int.MaxValue)]
+    public void RejectsEmptyNegativeOrHugeScreenSizes(int width, int height)
+    {
+        Assert.False(WindowsEventsManager.HasSaneBounds(width, height));
+    }
+}